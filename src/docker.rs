@@ -1,24 +1,280 @@
 use crate::format::CodeStr;
 use crossbeam::channel::{bounded, Sender};
 use indicatif::{ProgressBar, ProgressStyle};
+use jobserver::{Acquired, Client};
 use scopeguard::guard;
+use tar::Archive;
 use std::{
-  fs::{create_dir_all, metadata, rename},
+  collections::HashSet,
+  env,
+  fmt,
+  fs::create_dir_all,
   io,
   io::{Read, Write},
-  path::{Path, PathBuf},
-  process::{ChildStdin, Command, Stdio},
+  path::{Component, Path, PathBuf},
+  process::{
+    exit, Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio,
+  },
+  str::FromStr,
   sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    Arc, Mutex,
   },
   thread,
   thread::sleep,
   time::{Duration, Instant},
 };
-use tempfile::tempdir;
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
 use uuid::Uuid;
-use walkdir::WalkDir;
+
+// Whether to stream container output to the terminal as it arrives. When this
+// is `false` (the default), quiet commands buffer their output and only surface
+// it on failure; when `true`, the bytes are forwarded live while still being
+// captured for the error message.
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+// Enable or disable live output streaming. [tag:set_verbose]
+pub fn set_verbose(verbose: bool) {
+  VERBOSE.store(verbose, Ordering::SeqCst);
+}
+
+// Whether live output streaming is enabled.
+fn verbose() -> bool {
+  VERBOSE.load(Ordering::SeqCst)
+}
+
+// The requested number of tasks to run concurrently, or `0` to use the number
+// of CPUs. This must be set (via [ref:set_concurrency]) before the jobserver is
+// first used, after which it has no effect.
+static CONCURRENCY: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+  // The global GNU Make jobserver client. There is only ever one per process so
+  // that the token pool is shared across all tasks, and so that parallelism
+  // composes correctly when toast is itself invoked under `make -jN`.
+  static ref JOBSERVER: Client = {
+    // If we were invoked under a `make`-style jobserver (it advertises itself in
+    // `$MAKEFLAGS`, which Cargo also re-exports as `CARGO_MAKEFLAGS`), inherit
+    // its client so our tasks draw from the same token pool. `from_env` is
+    // `unsafe` because it trusts that the inherited file descriptors haven't
+    // been closed by other code; they haven't, since we call this before
+    // spawning anything. [tag:jobserver_from_env]
+    if let Some(client) = unsafe { Client::from_env() } {
+      client
+    } else {
+      // There's nothing to inherit, so create our own jobserver sized to the
+      // requested concurrency (defaulting to the number of CPUs). The token
+      // count excludes the implicit token every process already owns, so we
+      // only need room for the *additional* in-flight tasks — and a pool of
+      // zero (from `--concurrency 1`) correctly forces a serial build. The
+      // `unwrap` panics only if setting up the jobserver's pipe fails with an
+      // I/O error.
+      let concurrency = match CONCURRENCY.load(Ordering::SeqCst) {
+        0 => num_cpus::get(),
+        n => n,
+      };
+      Client::new(concurrency.saturating_sub(1)).unwrap()
+    }
+  };
+}
+
+// Set the requested concurrency. This must be called before any tasks are
+// scheduled, since the jobserver is sized lazily the first time it's used.
+// [tag:set_concurrency]
+pub fn set_concurrency(concurrency: usize) {
+  CONCURRENCY.store(concurrency, Ordering::SeqCst);
+}
+
+// Acquire a token from the jobserver before starting an additional concurrent
+// task. Every process implicitly owns one token, so the first task needn't call
+// this; each further task must hold an `Acquired` guard (which reads a byte from
+// the jobserver pipe) before it creates or starts its container, and the token
+// is released (the byte written back) when the guard is dropped.
+pub fn acquire(running: &Arc<AtomicBool>) -> Result<Acquired, String> {
+  JOBSERVER.acquire().map_err(|e| {
+    if running.load(Ordering::SeqCst) {
+      format!("Unable to acquire a jobserver token. Details: {}", e)
+    } else {
+      super::INTERRUPT_MESSAGE.to_owned()
+    }
+  })
+}
+
+// The container engine used to run every operation. Docker, Podman, and
+// nerdctl all speak nearly the same `container create/start/cp/commit`
+// dialect, so the only thing that usually varies between call sites is the name
+// of the executable. The one other difference handled here is `--init` support
+// (see `supports_init`); every other subcommand, including `cp`, is invoked the
+// same way for all three engines.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerEngine {
+  Docker,
+  Podman,
+  Nerdctl,
+}
+
+impl ContainerEngine {
+  // The name of the executable to invoke.
+  fn binary(self) -> &'static str {
+    match self {
+      Self::Docker => "docker",
+      Self::Podman => "podman",
+      Self::Nerdctl => "nerdctl",
+    }
+  }
+
+  // Whether the engine understands `container create --init`. Docker and Podman
+  // ship an init process (Tini) for zombie reaping and signal forwarding
+  // [ref:--init]; nerdctl has no equivalent flag, so we omit it there.
+  fn supports_init(self) -> bool {
+    match self {
+      Self::Docker | Self::Podman => true,
+      Self::Nerdctl => false,
+    }
+  }
+}
+
+impl fmt::Display for ContainerEngine {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.binary())
+  }
+}
+
+impl FromStr for ContainerEngine {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "docker" => Ok(Self::Docker),
+      "podman" => Ok(Self::Podman),
+      "nerdctl" => Ok(Self::Nerdctl),
+      _ => Err(format!(
+        "Unknown container engine {}. Expected {}, {}, or {}.",
+        s.code_str(),
+        "docker".code_str(),
+        "podman".code_str(),
+        "nerdctl".code_str(),
+      )),
+    }
+  }
+}
+
+// The explicitly selected engine, encoded as a small integer so it can live in
+// an atomic: `0` means "unset, auto-detect". This must be set (via
+// [ref:set_engine]) before the engine is first used, after which it has no
+// effect.
+static ENGINE_OVERRIDE: AtomicU8 = AtomicU8::new(0);
+
+lazy_static! {
+  // The container engine resolved once per process: the explicit `--engine`
+  // selection if given, otherwise an auto-detected default.
+  static ref ENGINE: ContainerEngine = {
+    match ENGINE_OVERRIDE.load(Ordering::SeqCst) {
+      1 => ContainerEngine::Docker,
+      2 => ContainerEngine::Podman,
+      3 => ContainerEngine::Nerdctl,
+      _ => detect_engine(),
+    }
+  };
+}
+
+// Record the user's explicit container engine choice. This must be called
+// before any engine operation is performed, since the engine is resolved lazily
+// the first time it's used. [tag:set_engine]
+pub fn set_engine(engine: ContainerEngine) {
+  ENGINE_OVERRIDE.store(
+    match engine {
+      ContainerEngine::Docker => 1,
+      ContainerEngine::Podman => 2,
+      ContainerEngine::Nerdctl => 3,
+    },
+    Ordering::SeqCst,
+  );
+}
+
+// Auto-detect which container engine to use. We honor the connection
+// environment variables first (`$DOCKER_HOST` points at a Docker daemon,
+// `$CONTAINER_HOST` at a Podman service), then fall back to whichever binary is
+// on the `PATH`, preferring Docker for backward compatibility.
+fn detect_engine() -> ContainerEngine {
+  if env::var_os("DOCKER_HOST").is_some() {
+    return ContainerEngine::Docker;
+  }
+  if env::var_os("CONTAINER_HOST").is_some() {
+    return ContainerEngine::Podman;
+  }
+  for engine in &[
+    ContainerEngine::Docker,
+    ContainerEngine::Podman,
+    ContainerEngine::Nerdctl,
+  ] {
+    if binary_on_path(engine.binary()) {
+      return *engine;
+    }
+  }
+
+  // Nothing was found, but we still have to return something. Default to Docker
+  // so the ensuing "command not found" error names the tool most users expect.
+  ContainerEngine::Docker
+}
+
+// Determine whether an executable with the given name exists on the `PATH`.
+fn binary_on_path(name: &str) -> bool {
+  env::var_os("PATH").map_or(false, |paths| {
+    env::split_paths(&paths).any(|dir| dir.join(name).is_file())
+  })
+}
+
+lazy_static! {
+  // The IDs of every container that has been created but not yet deleted. We
+  // track these so that an interrupted build can tear them down instead of
+  // leaking them.
+  static ref CONTAINERS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+// Remember a container so it can be cleaned up on interrupt. The `unwrap` is
+// safe because a panic is the only thing that can poison the lock, and we never
+// panic while holding it. [tag:register_container]
+fn register_container(container: &str) {
+  CONTAINERS.lock().unwrap().insert(container.to_owned());
+}
+
+// Forget a container once it has been deleted.
+fn deregister_container(container: &str) {
+  CONTAINERS.lock().unwrap().remove(container);
+}
+
+// Stop and delete every tracked container. This is best-effort: since it runs
+// while we're tearing down, individual failures are ignored.
+fn reap_containers(running: &Arc<AtomicBool>) {
+  // Drain the registry up front so we don't hold the lock across the (slow)
+  // engine calls. The `unwrap` is safe per [ref:register_container].
+  let containers: Vec<String> =
+    CONTAINERS.lock().unwrap().drain().collect();
+  for container in containers {
+    let _ = stop_container(&container, running);
+    let _ = delete_container(&container, running);
+  }
+}
+
+// Install a handler for SIGINT/SIGTERM that flips `running` to `false` and
+// reaps any tracked containers before exiting. Together with the per-child
+// process group set up in [ref:process_group], this ensures the terminal's
+// Ctrl-C reaches toast — which then decides how to tear down — rather than
+// killing the engine child out from under us and leaving dangling containers.
+// [tag:install_signal_handler]
+pub fn install_signal_handler(
+  running: &Arc<AtomicBool>,
+) -> Result<(), String> {
+  let running = running.clone();
+  ctrlc::set_handler(move || {
+    running.store(false, Ordering::SeqCst);
+    reap_containers(&running);
+    exit(1);
+  })
+  .map_err(|e| format!("Unable to install the signal handler. Details: {}", e))
+}
 
 // Construct a random image tag.
 pub fn random_tag() -> String {
@@ -111,24 +367,24 @@ pub fn create_container(
   // signals by explicitly trapping them. Tini traps these signals and forwards
   // them to the child process. Then the default signal handling behavior of
   // the child process (in our case, `/bin/sh`) works normally. [tag:--init]
-  Ok(
-    run_quiet(
-      "Creating container...",
-      "Unable to create container.",
-      vec![
-        "container",
-        "create",
-        "--init",
-        "--interactive",
-        image,
-        "/bin/sh",
-      ]
-      .as_ref(),
-      running,
-    )?
-    .trim()
-    .to_owned(),
-  )
+  let mut args = vec!["container", "create"];
+  if ENGINE.supports_init() {
+    args.push("--init");
+  }
+  args.extend_from_slice(&["--interactive", image, "/bin/sh"]);
+  let container = run_quiet(
+    "Creating container...",
+    "Unable to create container.",
+    args.as_ref(),
+    running,
+  )?
+  .trim()
+  .to_owned();
+
+  // Track the container so it can be reaped if toast is interrupted.
+  register_container(&container);
+
+  Ok(container)
 }
 
 // Copy files into a container.
@@ -173,112 +429,166 @@ pub fn copy_from_container(
       container.code_str()
     );
 
-    // `docker container cp` is not idempotent. For example, suppose there is a
-    // directory called `/foo` in the container and `/bar` does not exist on
-    // the host. Consider the following command:
-    //   `docker cp container:/foo /bar`
-    // The first time that command is run, Docker will create the directory
-    // `/bar` on the host and copy the files from `/foo` into it. But if you
-    // run it again, Docker will copy `/bar` into the directory `/foo`,
-    // resulting in `/foo/foo`, which is undesirable. To work around this, we
-    // first copy the path from the container into a temporary directory (where
-    // the target path is guaranteed to not exist). Then we copy/move that to
-    // the final destination.
-    let temp_dir = tempdir().map_err(|e| {
-      format!("Unable to create temporary directory. Details: {}", e)
-    })?;
-
     // Figure out what needs to go where.
     let source = source_dir.join(path);
-    let intermediate = temp_dir.path().join("data");
     let destination = destination_dir.join(path);
 
-    // Get the path from the container.
-    run_quiet(
-      "Copying files from the container...",
-      "Unable to copy files from the container.",
-      &[
-        "container",
-        "cp",
-        &format!("{}:{}", container, source.to_string_lossy()),
-        &intermediate.to_string_lossy(),
-      ],
-      running,
-    )
-    .map(|_| ())?;
+    let _guard = spin("Copying files from the container...");
+
+    // Ask the engine to write a tar of the source path to its standard output
+    // (the trailing `-`) and consume that stream in-process. This avoids the
+    // temporary-directory round trip and the per-entry `rename` the old
+    // implementation needed, and sidesteps the idempotency quirk of copying to
+    // a path on disk (`cp container:/foo /bar` nests `/foo` inside an existing
+    // `/bar`) because we rewrite the archive's leading path component ourselves.
+    let mut child = grouped_command(&[
+      "container",
+      "cp",
+      &format!("{}:{}", container, source.to_string_lossy()),
+      "-",
+    ])
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| {
+      format!("Unable to copy files from the container.\nDetails: {}", e)
+    })?;
+
+    // Drain the standard error stream on a separate thread so a chatty engine
+    // (`podman`/`nerdctl` `cp` can exceed a single pipe buffer) can't deadlock
+    // against our reading of the tar on standard output. The `unwrap` is safe
+    // because we requested a piped standard error.
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_handle = thread::spawn(move || {
+      let mut captured = String::new();
+      stderr.read_to_string(&mut captured).ok();
+      captured
+    });
+
+    // The `unwrap` is safe because we requested a piped standard output.
+    let mut archive = Archive::new(child.stdout.take().unwrap());
+    let unpack = unpack_archive(&mut archive, &destination);
+
+    // Drop the archive (and with it the read end of the pipe) before waiting,
+    // so that if we bailed out of `unpack_archive` early the child receives a
+    // broken pipe and exits rather than blocking on a full pipe.
+    drop(archive);
+
+    // Collect the captured standard error. The `unwrap` is safe because the
+    // spawned thread never panics.
+    let captured = stderr_handle.join().unwrap();
+
+    let status = child.wait().map_err(|e| {
+      format!("Unable to copy files from the container.\nDetails: {}", e)
+    })?;
 
-    // Check if what we got from the container is a file or a directory.
-    let metadata_err_map = |e| {
+    if !status.success() {
+      return Err(if status.code().is_none() {
+        running.store(false, Ordering::SeqCst);
+        super::INTERRUPT_MESSAGE.to_owned()
+      } else {
+        format!(
+          "Unable to copy files from the container.\nDetails: {}",
+          captured
+        )
+      });
+    }
+
+    // Surface any extraction error only once we know the copy itself succeeded.
+    unpack?;
+  }
+
+  Ok(())
+}
+
+// Map a tar entry's path onto the host `destination`. We drop an optional
+// leading `./` (some engines emit it) and then the archive's root component
+// (the container-side source's basename) so `foo/bar` lands at
+// `destination/bar`; a single-file source's lone entry maps straight onto
+// `destination`. Only normal components are kept — a `..` is rejected so a
+// malicious archive can't escape `destination`.
+fn rewrite_entry_path(
+  entry_path: &Path,
+  destination: &Path,
+) -> Result<PathBuf, String> {
+  let mut components = entry_path.components().peekable();
+
+  if let Some(Component::CurDir) = components.peek() {
+    components.next();
+  }
+
+  components.next();
+
+  let mut relative = PathBuf::new();
+  for component in components {
+    match component {
+      Component::Normal(part) => relative.push(part),
+      Component::CurDir => {}
+      _ => {
+        return Err(format!(
+          "Refusing to extract entry {} with an unsafe path from the \
+           container.",
+          entry_path.to_string_lossy().code_str()
+        ));
+      }
+    }
+  }
+
+  // Joining an empty path would append a trailing separator, which can't be
+  // created as a file, so return `destination` itself in that case.
+  if relative.as_os_str().is_empty() {
+    return Ok(destination.to_owned());
+  }
+
+  Ok(destination.join(relative))
+}
+
+// Unpack a tar stream from the container under `destination`, rewriting each
+// entry's leading path component (the container-side source's basename) so the
+// archive's contents land directly on the host output path.
+fn unpack_archive<R: Read>(
+  archive: &mut Archive<R>,
+  destination: &Path,
+) -> Result<(), String> {
+  let entries = archive.entries().map_err(|e| {
+    format!("Unable to read the tar stream from the container. Details: {}", e)
+  })?;
+
+  for entry in entries {
+    let mut entry = entry.map_err(|e| {
       format!(
-        "Unable to retrieve filesystem metadata for path {}. Details: {}",
-        intermediate.to_string_lossy().code_str(),
+        "Unable to read an entry from the container's tar stream. Details: {}",
         e
       )
-    };
-    if metadata(&intermediate).map_err(metadata_err_map)?.is_file() {
-      // It's a file. Determine the destination directory. The `unwrap` is safe
-      // because the root of the filesystem cannot be a file.
-      let destination_dir = destination.parent().unwrap().to_owned();
+    })?;
 
-      // Make sure the destination directory exists.
-      create_dir_all(&destination_dir).map_err(|e| {
-        format!(
-          "Unable to create directory {}. Details: {}",
-          destination_dir.to_string_lossy().code_str(),
-          e
-        )
-      })?;
+    let entry_path = entry
+      .path()
+      .map_err(|e| {
+        format!("Unable to read an entry path from the container. Details: {}", e)
+      })?
+      .into_owned();
+    let target = rewrite_entry_path(&entry_path, destination)?;
 
-      // Move it to the destination.
-      rename(&intermediate, &destination).map_err(|e| {
+    // Make sure the destination's parent directory exists.
+    if let Some(parent) = target.parent() {
+      create_dir_all(parent).map_err(|e| {
         format!(
-          "Unable to move file {} to destination {}. Details: {}",
-          intermediate.to_string_lossy().code_str(),
-          destination.to_string_lossy().code_str(),
+          "Unable to create directory {}. Details: {}",
+          parent.to_string_lossy().code_str(),
           e
         )
       })?;
-    } else {
-      // It's a directory. Traverse it.
-      for entry in WalkDir::new(&intermediate) {
-        // If we run into an error traversing the filesystem, report it.
-        let entry = entry.map_err(|e| {
-          format!(
-            "Unable to traverse directory {}. Details: {}",
-            intermediate.to_string_lossy().code_str(),
-            e
-          )
-        })?;
-
-        // Figure out what needs to go where. The `unwrap` is safe because
-        // `entry` is guaranteed to be inside `intermediate` (or equal to it).
-        let entry_path = entry.path();
-        let destination_path =
-          destination.join(entry_path.strip_prefix(&intermediate).unwrap());
-
-        // Check if the current entry is a file or a directory.
-        if entry.file_type().is_dir() {
-          // It's a directory. Create a directory at the destination.
-          create_dir_all(&destination_path).map_err(|e| {
-            format!(
-              "Unable to create directory {}. Details: {}",
-              destination_path.to_string_lossy().code_str(),
-              e
-            )
-          })?;
-        } else {
-          // It's a file. Move it to the destination.
-          rename(entry_path, &destination_path).map_err(|e| {
-            format!(
-              "Unable to move file {} to destination {}. Details: {}",
-              entry_path.to_string_lossy().code_str(),
-              destination_path.to_string_lossy().code_str(),
-              e
-            )
-          })?;
-        }
-      }
     }
+
+    entry.unpack(&target).map_err(|e| {
+      format!(
+        "Unable to extract {} from the container. Details: {}",
+        target.to_string_lossy().code_str(),
+        e
+      )
+    })?;
   }
 
   Ok(())
@@ -351,13 +661,19 @@ pub fn delete_container(
   running: &Arc<AtomicBool>,
 ) -> Result<(), String> {
   debug!("Deleting container {}\u{2026}", container.code_str());
-  run_quiet(
+  let result = run_quiet(
     "Deleting container...",
     "Unable to delete container.",
     &["container", "rm", "--force", container],
     running,
   )
-  .map(|_| ())
+  .map(|_| ());
+
+  // The container is gone (or failed to delete, which the error reports), so
+  // stop tracking it either way.
+  deregister_container(container);
+
+  result
 }
 
 // Run an interactive shell.
@@ -369,20 +685,13 @@ pub fn spawn_shell(
     "Spawning an interactive shell for image {}\u{2026}",
     image.code_str()
   );
-  run_attach(
-    "The shell exited with a failure.",
-    &[
-      "container",
-      "run",
-      "--rm",
-      "--interactive",
-      "--tty",
-      "--init", // [ref:--init]
-      image,
-      "/bin/su", // We use `su` rather than `sh` to use the root user's shell.
-    ],
-    running,
-  )
+  let mut args = vec!["container", "run", "--rm", "--interactive", "--tty"];
+  if ENGINE.supports_init() {
+    args.push("--init"); // [ref:--init]
+  }
+  // We use `su` rather than `sh` to use the root user's shell.
+  args.extend_from_slice(&[image, "/bin/su"]);
+  run_attach("The shell exited with a failure.", args.as_ref(), running)
 }
 
 // Run a command, forward its standard error stream, and return its standard
@@ -393,9 +702,22 @@ fn run_quiet(
   args: &[&str],
   running: &Arc<AtomicBool>,
 ) -> Result<String, String> {
+  // In verbose mode, forward the output live instead of buffering behind a
+  // spinner.
+  if verbose() {
+    let child = grouped_command(args)
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| format!("{}\nDetails: {}", error, e))?;
+
+    return stream_to_completion(child, error, running);
+  }
+
   let _guard = spin(spinner_message);
 
-  let output = command(args)
+  let output = grouped_command(args)
     .stdin(Stdio::null())
     .output()
     .map_err(|e| format!("{}\nDetails: {}", error, e))?;
@@ -426,9 +748,26 @@ fn run_quiet_stdin<W: FnOnce(&mut ChildStdin) -> Result<(), String>>(
   writer: W,
   running: &Arc<AtomicBool>,
 ) -> Result<String, String> {
+  // In verbose mode, forward the output live instead of buffering behind a
+  // spinner. We feed the child's standard input, close it, then tee the rest.
+  if verbose() {
+    let mut child = grouped_command(args)
+      .stdin(Stdio::piped()) // [tag:run_quiet_stdin_piped_verbose]
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| format!("{}\nDetails: {}", error, e))?;
+    writer(child.stdin.as_mut().unwrap())?; // [ref:run_quiet_stdin_piped_verbose]
+
+    // Drop the standard input handle so the child observes EOF and can exit.
+    drop(child.stdin.take());
+
+    return stream_to_completion(child, error, running);
+  }
+
   let _guard = spin(spinner_message);
 
-  let mut child = command(args)
+  let mut child = grouped_command(args)
     .stdin(Stdio::piped()) // [tag:run_quiet_stdin_piped]
     .stdout(Stdio::piped())
     .stderr(Stdio::piped())
@@ -464,7 +803,7 @@ fn run_loud_stdin<W: FnOnce(&mut ChildStdin) -> Result<(), String>>(
   writer: W,
   running: &Arc<AtomicBool>,
 ) -> Result<(), String> {
-  let mut child = command(args)
+  let mut child = grouped_command(args)
     .stdin(Stdio::piped()) // [tag:run_loud_stdin_piped]
     .spawn()
     .map_err(|e| format!("{}\nDetails: {}", error, e))?;
@@ -513,15 +852,240 @@ fn run_attach(
   }
 }
 
-// Construct a Docker `Command` from an array of arguments.
+// Wait for a child process, forwarding its standard output and error streams to
+// the terminal as the bytes arrive while tee-ing them into in-memory buffers.
+// The captured standard output is returned on success; the captured standard
+// error is folded into the error message on failure.
+fn stream_to_completion(
+  mut child: Child,
+  error: &str,
+  running: &Arc<AtomicBool>,
+) -> Result<String, String> {
+  // The `unwrap`s are safe because we always spawn with both pipes.
+  let stdout = child.stdout.take().unwrap();
+  let stderr = child.stderr.take().unwrap();
+
+  let mut out_buf = Vec::new();
+  let mut err_buf = Vec::new();
+  read2(stdout, stderr, &mut out_buf, &mut err_buf)
+    .map_err(|e| format!("{}\nDetails: {}", error, e))?;
+
+  let status = child
+    .wait()
+    .map_err(|e| format!("{}\nDetails: {}", error, e))?;
+
+  if status.success() {
+    Ok(String::from_utf8_lossy(&out_buf).to_string())
+  } else {
+    Err(if status.code().is_none() {
+      running.store(false, Ordering::SeqCst);
+      super::INTERRUPT_MESSAGE.to_owned()
+    } else {
+      format!("{}\nDetails: {}", error, String::from_utf8_lossy(&err_buf))
+    })
+  }
+}
+
+// Concurrently drain two pipes, forwarding each chunk to the corresponding
+// terminal stream and appending it to a capture buffer, until both reach EOF.
+// On Unix we put both descriptors in non-blocking mode and `poll` for whichever
+// is ready, which avoids the deadlock that would occur if we read one pipe to
+// completion while the other filled its kernel buffer.
+#[cfg(unix)]
+fn read2(
+  out_pipe: ChildStdout,
+  err_pipe: ChildStderr,
+  out_buf: &mut Vec<u8>,
+  err_buf: &mut Vec<u8>,
+) -> io::Result<()> {
+  use std::os::unix::io::AsRawFd;
+
+  set_nonblocking(out_pipe.as_raw_fd())?;
+  set_nonblocking(err_pipe.as_raw_fd())?;
+
+  // `Some` while the stream is still open; `None` once it hits EOF. A closed
+  // stream is represented to `poll` by a negative descriptor, which it ignores.
+  let mut out = Some(out_pipe);
+  let mut err = Some(err_pipe);
+  let mut chunk = [0_u8; 4096];
+
+  while out.is_some() || err.is_some() {
+    let mut fds = [
+      libc::pollfd {
+        fd: out.as_ref().map_or(-1, AsRawFd::as_raw_fd),
+        events: libc::POLLIN,
+        revents: 0,
+      },
+      libc::pollfd {
+        fd: err.as_ref().map_or(-1, AsRawFd::as_raw_fd),
+        events: libc::POLLIN,
+        revents: 0,
+      },
+    ];
+
+    if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } == -1 {
+      let e = io::Error::last_os_error();
+      if e.kind() == io::ErrorKind::Interrupted {
+        continue;
+      }
+      return Err(e);
+    }
+
+    if fds[0].revents != 0 {
+      if let Some(pipe) = out.as_mut() {
+        if drain(pipe, &mut chunk, out_buf, false)? {
+          out = None;
+        }
+      }
+    }
+    if fds[1].revents != 0 {
+      if let Some(pipe) = err.as_mut() {
+        if drain(pipe, &mut chunk, err_buf, true)? {
+          err = None;
+        }
+      }
+    }
+  }
+
+  Ok(())
+}
+
+// Read everything currently available from a non-blocking pipe, tee-ing it to
+// the terminal and the capture buffer. Returns `true` once the pipe reaches
+// EOF.
+#[cfg(unix)]
+fn drain<R: Read>(
+  pipe: &mut R,
+  chunk: &mut [u8],
+  capture: &mut Vec<u8>,
+  to_stderr: bool,
+) -> io::Result<bool> {
+  loop {
+    match pipe.read(chunk) {
+      Ok(0) => return Ok(true),
+      Ok(n) => {
+        capture.extend_from_slice(&chunk[..n]);
+        if to_stderr {
+          let stderr = io::stderr();
+          let mut stderr = stderr.lock();
+          stderr.write_all(&chunk[..n])?;
+          stderr.flush()?;
+        } else {
+          let stdout = io::stdout();
+          let mut stdout = stdout.lock();
+          stdout.write_all(&chunk[..n])?;
+          stdout.flush()?;
+        }
+      }
+      Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+      Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+// Put a file descriptor into non-blocking mode.
+#[cfg(unix)]
+fn set_nonblocking(fd: std::os::unix::io::RawFd) -> io::Result<()> {
+  let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+  if flags == -1 {
+    return Err(io::Error::last_os_error());
+  }
+  if unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } == -1 {
+    return Err(io::Error::last_os_error());
+  }
+  Ok(())
+}
+
+// On platforms without `poll`, fall back to a thread per pipe so that a full
+// kernel buffer on one stream can't block progress on the other.
+#[cfg(not(unix))]
+fn read2(
+  mut out_pipe: ChildStdout,
+  mut err_pipe: ChildStderr,
+  out_buf: &mut Vec<u8>,
+  err_buf: &mut Vec<u8>,
+) -> io::Result<()> {
+  let err_handle = thread::spawn(move || -> io::Result<Vec<u8>> {
+    let mut captured = Vec::new();
+    tee(&mut err_pipe, &mut captured, true)?;
+    Ok(captured)
+  });
+
+  tee(&mut out_pipe, out_buf, false)?;
+
+  // The `unwrap` is safe because the spawned thread never panics.
+  *err_buf = err_handle.join().unwrap()?;
+  Ok(())
+}
+
+// Blocking tee of a pipe to the terminal and a capture buffer until EOF.
+#[cfg(not(unix))]
+fn tee<R: Read>(
+  pipe: &mut R,
+  capture: &mut Vec<u8>,
+  to_stderr: bool,
+) -> io::Result<()> {
+  let mut chunk = [0_u8; 4096];
+  loop {
+    match pipe.read(&mut chunk) {
+      Ok(0) => return Ok(()),
+      Ok(n) => {
+        capture.extend_from_slice(&chunk[..n]);
+        if to_stderr {
+          let stderr = io::stderr();
+          let mut stderr = stderr.lock();
+          stderr.write_all(&chunk[..n])?;
+          stderr.flush()?;
+        } else {
+          let stdout = io::stdout();
+          let mut stdout = stdout.lock();
+          stdout.write_all(&chunk[..n])?;
+          stdout.flush()?;
+        }
+      }
+      Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    }
+  }
+}
+
+// Construct a `Command` for the active container engine from an array of
+// arguments.
 fn command(args: &[&str]) -> Command {
-  let mut command = Command::new("docker");
+  let mut command = Command::new(ENGINE.binary());
   for arg in args {
     command.arg(arg);
   }
   command
 }
 
+// Construct a `Command` for a child that toast drives itself (the piped
+// `run_quiet*`/`run_loud_stdin` spawns), isolating it in its own process group.
+// This way a Ctrl-C typed in the terminal is delivered to toast rather than
+// straight to the engine child; toast's signal handler
+// ([ref:install_signal_handler]) then reaps the tracked containers and exits;
+// the signal is not sent on to the engine child's process group. We
+// deliberately do *not* do this for the
+// interactive `run_attach` child, which needs to stay in the terminal's
+// foreground process group to use the inherited TTY. [tag:process_group]
+fn grouped_command(args: &[&str]) -> Command {
+  let mut command = command(args);
+
+  #[cfg(unix)]
+  unsafe {
+    command.pre_exec(|| {
+      if libc::setpgid(0, 0) == -1 {
+        return Err(io::Error::last_os_error());
+      }
+
+      Ok(())
+    });
+  }
+
+  command
+}
+
 // Render a spinner in the terminal. When the returned value is dropped, the
 // spinner is stopped.
 fn spin(message: &str) -> impl Drop {
@@ -601,10 +1165,58 @@ fn spin(message: &str) -> impl Drop {
 
 #[cfg(test)]
 mod tests {
-  use crate::docker::random_tag;
+  use crate::docker::{
+    random_tag, rewrite_entry_path, ContainerEngine,
+  };
+  use std::path::{Path, PathBuf};
 
   #[test]
   fn random_impure() {
     assert_ne!(random_tag(), random_tag());
   }
+
+  #[test]
+  fn engine_from_str_valid() {
+    assert_eq!("docker".parse(), Ok(ContainerEngine::Docker));
+    assert_eq!("podman".parse(), Ok(ContainerEngine::Podman));
+    assert_eq!("nerdctl".parse(), Ok(ContainerEngine::Nerdctl));
+  }
+
+  #[test]
+  fn engine_from_str_invalid() {
+    let error = "containerd".parse::<ContainerEngine>().unwrap_err();
+    assert!(error.contains("containerd"));
+  }
+
+  #[test]
+  fn rewrite_entry_path_directory() {
+    assert_eq!(
+      rewrite_entry_path(Path::new("foo/bar"), Path::new("/out")),
+      Ok(PathBuf::from("/out/bar"))
+    );
+  }
+
+  #[test]
+  fn rewrite_entry_path_single_file() {
+    assert_eq!(
+      rewrite_entry_path(Path::new("foo"), Path::new("/out")),
+      Ok(PathBuf::from("/out"))
+    );
+  }
+
+  #[test]
+  fn rewrite_entry_path_leading_cur_dir() {
+    assert_eq!(
+      rewrite_entry_path(Path::new("./foo/bar"), Path::new("/out")),
+      Ok(PathBuf::from("/out/bar"))
+    );
+  }
+
+  #[test]
+  fn rewrite_entry_path_rejects_escape() {
+    assert!(
+      rewrite_entry_path(Path::new("foo/../../etc/x"), Path::new("/out"))
+        .is_err()
+    );
+  }
 }